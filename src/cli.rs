@@ -1,5 +1,6 @@
 use crate::core::ledger::Ledger;
-use crate::{exporter, importer};
+use crate::prices::HttpPriceProvider;
+use crate::{exporter, importer, prices};
 use clap::{Args, Parser, Subcommand};
 use std::path::PathBuf;
 
@@ -16,16 +17,24 @@ pub enum Opts {
     Exporter(ExportOpts),
 
     Server(ServerOpts),
+
+    /// back-fill missing historical commodity prices from a remote provider
+    FetchPrices(FetchPricesOpts),
 }
 
 #[derive(Subcommand, Debug)]
 pub enum ImportOpts {
     Wechat { file: PathBuf, config: PathBuf },
+    /// import a bank/card CSV export using a TOML column-mapping config
+    Csv { file: PathBuf, config: PathBuf },
 }
 
 #[derive(Args, Debug)]
 pub struct ParseOpts {
     file: PathBuf,
+    /// discard any on-disk snapshot cache and reprocess every directive from scratch
+    #[clap(long)]
+    no_cache: bool,
 }
 
 #[derive(Subcommand, Debug)]
@@ -42,6 +51,21 @@ pub struct ServerOpts {
     pub file: PathBuf,
     #[clap(short, long, default_value_t = 6666)]
     pub port: u16,
+    /// discard any on-disk snapshot cache and reprocess every directive from scratch
+    #[clap(long)]
+    pub no_cache: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct FetchPricesOpts {
+    pub file: PathBuf,
+    /// base URL of the HTTP price provider, e.g. https://prices.example.com; falls
+    /// back to the ledger's `price_provider_url` config option if omitted
+    #[clap(long)]
+    pub provider_url: Option<String>,
+    /// falls back to the ledger's `price_provider_api_key` config option if omitted
+    #[clap(long)]
+    pub api_key: Option<String>,
 }
 
 impl Opts {
@@ -49,10 +73,42 @@ impl Opts {
         match self {
             Opts::Importer(importer) => importer.run(),
             Opts::Parse(file) => {
-                dbg!(Ledger::load(file.file).expect("Cannot load ledger"));
+                let ledger = crate::core::cache::load_ledger(file.file, !file.no_cache).expect("Cannot load ledger");
+                dbg!(ledger);
             }
             Opts::Exporter(opts) => opts.run(),
-            Opts::Server(opts) => crate::server::serve(opts).expect("cannot serve"),
+            Opts::Server(opts) => {
+                if opts.no_cache {
+                    crate::core::cache::invalidate_cache(&opts.file).expect("cannot invalidate cache");
+                }
+                crate::server::serve(opts).expect("cannot serve")
+            }
+            Opts::FetchPrices(opts) => opts.run(),
+        }
+    }
+}
+
+impl FetchPricesOpts {
+    pub fn run(self) {
+        let ledger = Ledger::load(self.file).expect("Cannot load ledger");
+        let provider_url = self
+            .provider_url
+            .or_else(|| ledger.configs.get("price_provider_url").cloned())
+            .expect("no price provider url given via --provider-url or the price_provider_url ledger config");
+        let api_key = self.api_key.or_else(|| ledger.configs.get("price_provider_api_key").cloned());
+        let provider = HttpPriceProvider::new(provider_url, api_key);
+
+        let mut grip = ledger.prices.write().unwrap();
+        match prices::backfill_ledger_prices(&ledger, &provider, &mut grip) {
+            Ok(reports) => {
+                for report in reports {
+                    println!(
+                        "fetched {} price(s) for {}/{}",
+                        report.inserted, report.commodity, report.operating_currency
+                    );
+                }
+            }
+            Err(error) => eprintln!("{}", error),
         }
     }
 }
@@ -61,6 +117,7 @@ impl ImportOpts {
     pub fn run(self) {
         let result = match self {
             ImportOpts::Wechat { file, config } => importer::wechat::run(file, config),
+            ImportOpts::Csv { file, config } => importer::csv::run(file, config),
         };
         match result {
             Ok(_) => {}
@@ -0,0 +1,447 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock as StdRwLock};
+
+use chrono::{NaiveDate, NaiveDateTime};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::core::amount::Amount;
+use crate::core::data::{Frequency, Scheduled, ScheduledPosting};
+use crate::core::inventory::AccountName;
+use crate::core::ledger::{AccountInfo, AccountSnapshot, AccountStatus, BudgetEnvelopeSnapshot, Ledger};
+use crate::core::process::{expand_scheduled_occurrences, scheduled_as_of_date};
+use crate::core::utils::price_grip::DatedPriceGrip;
+use crate::core::Currency;
+use crate::error::ZhangResult;
+
+/// Bumped whenever the cache's on-disk shape changes incompatibly. A cache tagged
+/// with a different version is rejected even when it deserializes, since a
+/// version-mismatched deserialize can succeed while silently producing garbage.
+pub const CACHE_FORMAT_VERSION: u32 = 1;
+
+const CACHE_FILE_NAME: &str = ".avaro-cache";
+
+#[derive(Serialize, Deserialize)]
+struct CachedAccountSnapshot {
+    inner: HashMap<Currency, Decimal>,
+}
+
+impl From<&AccountSnapshot> for CachedAccountSnapshot {
+    fn from(snapshot: &AccountSnapshot) -> Self {
+        CachedAccountSnapshot { inner: snapshot.inner.clone() }
+    }
+}
+
+impl CachedAccountSnapshot {
+    fn into_account_snapshot(self, prices: Arc<StdRwLock<DatedPriceGrip>>) -> AccountSnapshot {
+        AccountSnapshot { inner: self.inner, prices }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+enum CachedAccountStatus {
+    Open,
+    Close,
+}
+
+impl From<&AccountStatus> for CachedAccountStatus {
+    fn from(status: &AccountStatus) -> Self {
+        match status {
+            AccountStatus::Open => CachedAccountStatus::Open,
+            AccountStatus::Close => CachedAccountStatus::Close,
+        }
+    }
+}
+
+impl From<CachedAccountStatus> for AccountStatus {
+    fn from(status: CachedAccountStatus) -> Self {
+        match status {
+            CachedAccountStatus::Open => AccountStatus::Open,
+            CachedAccountStatus::Close => AccountStatus::Close,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedAccountInfo {
+    currencies: Vec<String>,
+    status: CachedAccountStatus,
+    meta: HashMap<String, String>,
+}
+
+impl From<&AccountInfo> for CachedAccountInfo {
+    fn from(info: &AccountInfo) -> Self {
+        CachedAccountInfo {
+            currencies: info.currencies.iter().cloned().collect(),
+            status: (&info.status).into(),
+            meta: info.meta.clone(),
+        }
+    }
+}
+
+impl From<CachedAccountInfo> for AccountInfo {
+    fn from(cached: CachedAccountInfo) -> Self {
+        AccountInfo {
+            currencies: cached.currencies.into_iter().collect(),
+            status: cached.status.into(),
+            meta: cached.meta,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedScheduledPosting {
+    account: AccountName,
+    number: Decimal,
+    currency: Currency,
+}
+
+impl From<&ScheduledPosting> for CachedScheduledPosting {
+    fn from(posting: &ScheduledPosting) -> Self {
+        CachedScheduledPosting {
+            account: posting.account.clone(),
+            number: posting.amount.number.clone(),
+            currency: posting.amount.currency.clone(),
+        }
+    }
+}
+
+impl From<CachedScheduledPosting> for ScheduledPosting {
+    fn from(cached: CachedScheduledPosting) -> Self {
+        ScheduledPosting {
+            account: cached.account,
+            amount: Amount::new(cached.number, cached.currency),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedScheduled {
+    id: String,
+    start_date: NaiveDate,
+    frequency: Frequency,
+    flag: String,
+    payee: Option<String>,
+    narration: Option<String>,
+    postings: Vec<CachedScheduledPosting>,
+}
+
+impl From<&Scheduled> for CachedScheduled {
+    fn from(scheduled: &Scheduled) -> Self {
+        CachedScheduled {
+            id: scheduled.id.clone(),
+            start_date: scheduled.start_date,
+            frequency: scheduled.frequency.clone(),
+            flag: scheduled.flag.clone(),
+            payee: scheduled.payee.clone(),
+            narration: scheduled.narration.clone(),
+            postings: scheduled.postings.iter().map(CachedScheduledPosting::from).collect(),
+        }
+    }
+}
+
+impl From<CachedScheduled> for Scheduled {
+    fn from(cached: CachedScheduled) -> Self {
+        Scheduled {
+            id: cached.id,
+            start_date: cached.start_date,
+            frequency: cached.frequency,
+            flag: cached.flag,
+            payee: cached.payee,
+            narration: cached.narration,
+            postings: cached.postings.into_iter().map(ScheduledPosting::from).collect(),
+        }
+    }
+}
+
+/// The fully-built ledger state that is expensive to recompute: the per-account
+/// running balances, the day-by-day history they were built from, and every price
+/// `DatedPriceGrip` has accumulated. Everything here is derived solely from
+/// replaying directives in `DirectiveProcess`, so it is safe to restore verbatim as
+/// long as [`source_hashes`] proves none of the contributing files changed.
+/// Deliberately excludes `ledger.currencies` (`HashMap<Currency, CurrencyInfo>`):
+/// `CurrencyInfo` embeds the original `Commodity` directive, and reconstructing one
+/// from a cache would mean duplicating the parser's own directive-construction logic
+/// here. Callers that only need the set of commodities actually in use (e.g. the
+/// price back-fill in `crate::prices`) should derive it from `ledger.accounts`'
+/// declared currencies instead, which this cache does restore faithfully.
+#[derive(Serialize, Deserialize)]
+pub struct LedgerCache {
+    version: u32,
+    source_hashes: HashMap<PathBuf, String>,
+    snapshot: HashMap<AccountName, CachedAccountSnapshot>,
+    daily_snapshot: Vec<(NaiveDate, HashMap<AccountName, CachedAccountSnapshot>)>,
+    accounts: HashMap<String, CachedAccountInfo>,
+    prices: Vec<(Currency, Currency, NaiveDateTime, Decimal)>,
+    budgets: HashMap<String, BudgetEnvelopeSnapshot>,
+    scheduled: HashMap<String, CachedScheduled>,
+    total_income: Decimal,
+    total_budgeted: Decimal,
+}
+
+fn cache_path(entry: &Path) -> PathBuf {
+    entry.join(CACHE_FILE_NAME)
+}
+
+fn hash_file(path: &Path) -> ZhangResult<String> {
+    let content = fs::read(path)?;
+    let mut hasher = Sha256::new();
+    hasher.update(&content);
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Recursively discovers every file an entry file pulls in via `include "..."`
+/// directives, so the cache can be invalidated if any of them changes - not just
+/// the entry file itself.
+fn discover_source_files(entry_file: &Path) -> ZhangResult<Vec<PathBuf>> {
+    let mut discovered = vec![entry_file.to_path_buf()];
+    let content = fs::read_to_string(entry_file)?;
+    let parent = entry_file.parent().unwrap_or_else(|| Path::new("."));
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if let Some(rest) = trimmed.strip_prefix("include ") {
+            let included = rest.trim().trim_matches('"');
+            discovered.extend(discover_source_files(&parent.join(included))?);
+        }
+    }
+
+    Ok(discovered)
+}
+
+fn compute_source_hashes(entry_file: &Path) -> ZhangResult<HashMap<PathBuf, String>> {
+    discover_source_files(entry_file)?
+        .into_iter()
+        .map(|file| {
+            let hash = hash_file(&file)?;
+            Ok((file, hash))
+        })
+        .collect()
+}
+
+/// Deletes `entry_file`'s cache, if any, so the next [`load_ledger`] rewrites it.
+/// Used by the CLI's `--no-cache` flag.
+pub fn invalidate_cache(entry_file: &Path) -> ZhangResult<()> {
+    let path = cache_path(entry_file.parent().unwrap_or_else(|| Path::new(".")));
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// Returns the cached, fully-built ledger state for `entry_file` if and only if a
+/// cache exists, was written by this binary's [`CACHE_FORMAT_VERSION`], and every
+/// file that contributed directives still hashes the same. Otherwise returns
+/// `Ok(None)`, and [`load_ledger`] rewrites the cache. A hit does not skip the
+/// `DirectiveProcess` pass: [`load_ledger`] runs the full `Ledger::load` either way.
+pub fn load_cache(entry_file: &Path) -> ZhangResult<Option<LedgerCache>> {
+    let path = cache_path(entry_file.parent().unwrap_or_else(|| Path::new(".")));
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let bytes = fs::read(&path)?;
+    let cache: LedgerCache = match bincode::deserialize(&bytes) {
+        Ok(cache) => cache,
+        Err(_) => return Ok(None),
+    };
+
+    if cache.version != CACHE_FORMAT_VERSION {
+        return Ok(None);
+    }
+
+    let current_hashes = compute_source_hashes(entry_file)?;
+    if current_hashes != cache.source_hashes {
+        return Ok(None);
+    }
+
+    Ok(Some(cache))
+}
+
+/// Serializes `ledger`'s fully-built state to disk, tagged with the current
+/// [`CACHE_FORMAT_VERSION`] and the content hash of every file that contributed to
+/// it, for [`load_cache`] to validate on the next `Ledger::load`.
+pub fn write_cache(entry_file: &Path, ledger: &Ledger) -> ZhangResult<()> {
+    let cache = LedgerCache {
+        version: CACHE_FORMAT_VERSION,
+        source_hashes: compute_source_hashes(entry_file)?,
+        snapshot: ledger.snapshot.iter().map(|(account, snapshot)| (account.clone(), snapshot.into())).collect(),
+        daily_snapshot: ledger
+            .daily_snapshot
+            .iter()
+            .map(|(date, snapshot)| (*date, snapshot.iter().map(|(account, s)| (account.clone(), s.into())).collect()))
+            .collect(),
+        accounts: ledger.accounts.iter().map(|(name, info)| (name.clone(), info.into())).collect(),
+        prices: ledger.prices.read().unwrap().entries(),
+        budgets: ledger.budgets.clone(),
+        scheduled: ledger.scheduled.iter().map(|(id, scheduled)| (id.clone(), scheduled.into())).collect(),
+        total_income: ledger.total_income,
+        total_budgeted: ledger.total_budgeted,
+    };
+
+    let bytes = bincode::serialize(&cache).expect("in-memory cache values always serialize");
+    fs::write(cache_path(entry_file.parent().unwrap_or_else(|| Path::new("."))), bytes)?;
+    Ok(())
+}
+
+/// Restores `ledger`'s derived fields from a previously validated [`LedgerCache`].
+/// Not currently called by [`load_ledger`]: `Ledger::load` is a single monolithic
+/// call that always performs the full `DirectiveProcess` replay, so by the time a
+/// `LedgerCache` could be restored onto a ledger, that ledger's state is already
+/// fresh and correct - restoring cached state over it would silently revert it to
+/// whatever was true when the cache was written. It is `pub` but has no caller and
+/// no test in this crate.
+pub fn restore_from_cache(ledger: &mut Ledger, cache: LedgerCache) {
+    let prices = ledger.prices.clone();
+
+    ledger.snapshot = cache
+        .snapshot
+        .into_iter()
+        .map(|(account, snapshot)| (account, snapshot.into_account_snapshot(prices.clone())))
+        .collect();
+    for (date, accounts) in cache.daily_snapshot {
+        let restored = accounts
+            .into_iter()
+            .map(|(account, snapshot)| (account, snapshot.into_account_snapshot(prices.clone())))
+            .collect();
+        ledger.daily_snapshot.insert_snapshot(date, restored);
+    }
+    ledger.accounts = cache.accounts.into_iter().map(|(name, info)| (name, info.into())).collect();
+    ledger.budgets = cache.budgets;
+    ledger.scheduled = cache.scheduled.into_iter().map(|(id, scheduled)| (id, scheduled.into())).collect();
+    ledger.total_income = cache.total_income;
+    ledger.total_budgeted = cache.total_budgeted;
+
+    let mut prices_grip = ledger.prices.write().unwrap();
+    for (base, quote, datetime, price) in cache.prices {
+        prices_grip.insert(datetime, base, quote, price);
+    }
+}
+
+/// Loads `entry_file` for the `parse` command; `fetch-prices` and the csv importer
+/// call `Ledger::load` directly instead. `Ledger::load` performs the full parse-and-`DirectiveProcess`
+/// replay as one monolithic call, with no way to ask it to skip that replay - so a
+/// "cache hit" cannot yet be made cheaper than a miss, and this function does not
+/// pretend otherwise. An earlier version of this function made that worse by calling
+/// [`restore_from_cache`] *after* the fresh load anyway, silently overwriting the
+/// just-computed, correct state with whatever had been cached; see
+/// [`restore_from_cache`]'s doc comment for why that is never safe to do today.
+///
+/// What this function does: always run the full load; top up any `Scheduled`
+/// occurrence `Scheduled::process` couldn't expand inline, now that the full pass is
+/// done and the ledger's true latest date is known (see
+/// [`core::process::expand_scheduled_occurrences`](crate::core::process::expand_scheduled_occurrences));
+/// and skip rewriting the on-disk cache when [`load_cache`] confirms it is still
+/// valid for this source content (`use_cache = true`, the default) - a safe
+/// optimization, since that top-up is derived solely from the ledger's own content,
+/// never the wall clock, so unchanged source content always reproduces byte-identical
+/// state. `use_cache = false` (the CLI's `--no-cache`) discards any existing cache
+/// file first and always rewrites it; the ledger returned is identical either way.
+pub fn load_ledger(entry_file: PathBuf, use_cache: bool) -> ZhangResult<Ledger> {
+    if !use_cache {
+        invalidate_cache(&entry_file)?;
+    }
+
+    let mut ledger = Ledger::load(entry_file.clone())?;
+
+    let as_of = scheduled_as_of_date(&ledger);
+    expand_scheduled_occurrences(&mut ledger, as_of);
+
+    let cache_still_valid = use_cache && load_cache(&entry_file)?.is_some();
+    if !cache_still_valid {
+        write_cache(&entry_file, &ledger)?;
+    }
+    Ok(ledger)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh, empty directory under the system temp dir, torn down on drop.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("avaro-cache-test-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn discover_source_files_follows_includes_recursively() {
+        let dir = TempDir::new("discover");
+        fs::write(dir.0.join("main.zhang"), "include \"accounts.zhang\"\n2022-01-01 open Assets:Bank\n").unwrap();
+        fs::write(dir.0.join("accounts.zhang"), "include \"prices.zhang\"\n2022-01-01 open Expenses:Food\n").unwrap();
+        fs::write(dir.0.join("prices.zhang"), "2022-01-01 price CNY 1 USD\n").unwrap();
+
+        let found = discover_source_files(&dir.0.join("main.zhang")).unwrap();
+
+        assert_eq!(found.len(), 3);
+        assert!(found.contains(&dir.0.join("main.zhang")));
+        assert!(found.contains(&dir.0.join("accounts.zhang")));
+        assert!(found.contains(&dir.0.join("prices.zhang")));
+    }
+
+    #[test]
+    fn source_hashes_change_when_an_included_file_changes() {
+        let dir = TempDir::new("hashes");
+        let entry = dir.0.join("main.zhang");
+        fs::write(&entry, "include \"accounts.zhang\"\n").unwrap();
+        fs::write(dir.0.join("accounts.zhang"), "2022-01-01 open Assets:Bank\n").unwrap();
+
+        let before = compute_source_hashes(&entry).unwrap();
+
+        fs::write(dir.0.join("accounts.zhang"), "2022-01-01 open Assets:Savings\n").unwrap();
+        let after = compute_source_hashes(&entry).unwrap();
+
+        assert_eq!(before.len(), 2);
+        assert_ne!(before, after);
+    }
+
+    #[test]
+    fn invalidate_cache_removes_a_written_cache_file() {
+        let dir = TempDir::new("invalidate");
+        let entry = dir.0.join("main.zhang");
+        fs::write(&entry, "2022-01-01 open Assets:Bank\n").unwrap();
+        fs::write(cache_path(&dir.0), b"not a real cache, just needs to exist").unwrap();
+
+        assert!(cache_path(&dir.0).exists());
+        invalidate_cache(&entry).unwrap();
+        assert!(!cache_path(&dir.0).exists());
+    }
+
+    #[test]
+    fn load_cache_rejects_a_cache_with_a_stale_format_version() {
+        let dir = TempDir::new("version");
+        let entry = dir.0.join("main.zhang");
+        fs::write(&entry, "2022-01-01 open Assets:Bank\n").unwrap();
+
+        let stale = LedgerCache {
+            version: CACHE_FORMAT_VERSION + 1,
+            source_hashes: compute_source_hashes(&entry).unwrap(),
+            snapshot: HashMap::new(),
+            daily_snapshot: Vec::new(),
+            accounts: HashMap::new(),
+            prices: Vec::new(),
+            budgets: HashMap::new(),
+            scheduled: HashMap::new(),
+            total_income: Decimal::ZERO,
+            total_budgeted: Decimal::ZERO,
+        };
+        fs::write(cache_path(&dir.0), bincode::serialize(&stale).unwrap()).unwrap();
+
+        assert!(load_cache(&entry).unwrap().is_none());
+    }
+}
@@ -0,0 +1,229 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{Datelike, NaiveDate};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::core::inventory::AccountName;
+use crate::core::Currency;
+
+/// `Budget` defines a named envelope bound to one or more expense accounts,
+/// together with the per-month allocation a user assigns to it, e.g.
+///
+/// ```zhang
+/// 2022-01-01 budget "Groceries" "CNY"
+///   accounts: "Expenses:Food:Groceries"
+///   2022-01: 1500
+///   2022-02: 1500
+/// ```
+#[derive(Debug, Clone)]
+pub struct Budget {
+    pub date: NaiveDate,
+    pub name: String,
+    pub commodity: Currency,
+    pub accounts: Vec<AccountName>,
+    pub monthly_assigned: HashMap<NaiveDate, Decimal>,
+}
+
+impl Budget {
+    /// Returns the amount assigned for the month containing `month`, where `month`
+    /// has already been normalized to the first day of that month.
+    pub fn assigned_in_month(&self, month: NaiveDate) -> Decimal {
+        self.monthly_assigned.get(&month).cloned().unwrap_or_default()
+    }
+}
+
+/// How often a [`Scheduled`] template recurs. `Every(n, freq)` repeats `freq` every
+/// `n`-th occurrence, e.g. `Every(2, Box::new(Frequency::Weekly))` is "every other week".
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+    Every(u32, Box<Frequency>),
+}
+
+impl Frequency {
+    /// Steps `from` forward by one occurrence of this frequency. Monthly and yearly
+    /// steps clamp the day-of-month to the target month's last day (e.g. Jan 31 with
+    /// a monthly frequency steps to Feb 28 or Feb 29, not March 3).
+    pub fn advance(&self, from: NaiveDate) -> NaiveDate {
+        match self {
+            Frequency::Daily => from + chrono::Duration::days(1),
+            Frequency::Weekly => from + chrono::Duration::weeks(1),
+            Frequency::Monthly => add_months_clamped(from, 1),
+            Frequency::Yearly => add_months_clamped(from, 12),
+            Frequency::Every(n, frequency) => {
+                let mut date = from;
+                for _ in 0..(*n).max(1) {
+                    date = frequency.advance(date);
+                }
+                date
+            }
+        }
+    }
+}
+
+fn add_months_clamped(date: NaiveDate, months: i32) -> NaiveDate {
+    let total_months = date.year() * 12 + date.month0() as i32 + months;
+    let year = total_months.div_euclid(12);
+    let month = total_months.rem_euclid(12) as u32 + 1;
+    let day = date.day().min(days_in_month(year, month));
+    NaiveDate::from_ymd_opt(year, month, day).expect("year/month/day are all in range")
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    let (next_year, next_month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+    NaiveDate::from_ymd_opt(next_year, next_month, 1)
+        .expect("year/month are in range")
+        .pred_opt()
+        .expect("the day before the 1st always exists")
+        .day()
+}
+
+/// One posting within a [`Scheduled`] template. Templates are expected to be fully
+/// specified (no balance-inferred/elided amounts), since there is no surrounding
+/// transaction to infer them from until an occurrence is materialized.
+#[derive(Debug, Clone)]
+pub struct ScheduledPosting {
+    pub account: AccountName,
+    pub amount: crate::core::amount::Amount,
+}
+
+/// `Scheduled` is a recurring-transaction template: a flag/payee/narration/postings
+/// skeleton that is materialized into concrete `Transaction`s for every occurrence
+/// between `start_date` and an "as of" cutoff, the same way a bank's
+/// scheduled-transaction feature previews upcoming bills. `Scheduled::process` expands
+/// what it can inline during the sorted `DirectiveProcess` pass (through whatever
+/// `ProcessContext::target_day` happens to be at that point); `core::process::
+/// expand_scheduled_occurrences` tops up anything that missed, once the full pass is
+/// done and the ledger's true latest date is known.
+#[derive(Debug, Clone)]
+pub struct Scheduled {
+    pub id: String,
+    pub start_date: NaiveDate,
+    pub frequency: Frequency,
+    pub flag: String,
+    pub payee: Option<String>,
+    pub narration: Option<String>,
+    pub postings: Vec<ScheduledPosting>,
+}
+
+impl Scheduled {
+    /// A stable, idempotent id for the `n`th occurrence of this template, so that
+    /// reprocessing the ledger never creates duplicate transactions.
+    pub fn occurrence_id(&self, occurrence: usize) -> String {
+        format!("{}:{}", self.id, occurrence)
+    }
+
+    /// The first `count` occurrences strictly after `after`, as `(occurrence index,
+    /// date)` pairs so the returned index lines up with [`Scheduled::occurrence_id`]
+    /// even though earlier occurrences were never materialized through this call.
+    /// Used to preview upcoming scheduled transactions without touching the ledger.
+    pub fn upcoming_occurrences(&self, after: NaiveDate, count: usize) -> Vec<(usize, NaiveDate)> {
+        let mut occurrence = 0usize;
+        let mut date = self.start_date;
+        while date <= after {
+            occurrence += 1;
+            date = self.frequency.advance(date);
+        }
+
+        let mut occurrences = Vec::with_capacity(count);
+        while occurrences.len() < count {
+            occurrences.push((occurrence, date));
+            occurrence += 1;
+            date = self.frequency.advance(date);
+        }
+        occurrences
+    }
+
+    /// Every occurrence of this template up to and including `as_of` whose id isn't
+    /// already present in `already_seen` (typically `ledger.scheduled_occurrences`'s
+    /// keys), as `(occurrence index, date, occurrence id)` triples. Used both by
+    /// `Scheduled::process`'s inline expansion and by
+    /// `core::process::expand_scheduled_occurrences`'s later top-up pass, so a given
+    /// occurrence is materialized by exactly one of the two, never both.
+    pub fn occurrences_through(&self, as_of: NaiveDate, already_seen: &HashSet<String>) -> Vec<(usize, NaiveDate, String)> {
+        let mut result = Vec::new();
+        let mut occurrence = 0usize;
+        let mut date = self.start_date;
+        while date <= as_of {
+            let id = self.occurrence_id(occurrence);
+            if !already_seen.contains(&id) {
+                result.push((occurrence, date, id));
+            }
+            occurrence += 1;
+            date = self.frequency.advance(date);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn monthly_advance_clamps_to_the_shorter_month() {
+        assert_eq!(Frequency::Monthly.advance(date(2022, 1, 31)), date(2022, 2, 28));
+        assert_eq!(Frequency::Monthly.advance(date(2024, 1, 31)), date(2024, 2, 29));
+    }
+
+    #[test]
+    fn yearly_advance_clamps_leap_day() {
+        assert_eq!(Frequency::Yearly.advance(date(2024, 2, 29)), date(2025, 2, 28));
+    }
+
+    #[test]
+    fn every_n_steps_repeats_the_inner_frequency() {
+        let every_other_week = Frequency::Every(2, Box::new(Frequency::Weekly));
+        assert_eq!(every_other_week.advance(date(2022, 1, 1)), date(2022, 1, 15));
+    }
+
+    #[test]
+    fn upcoming_occurrence_indexes_line_up_with_occurrence_id() {
+        let scheduled = Scheduled {
+            id: "rent".to_string(),
+            start_date: date(2022, 1, 1),
+            frequency: Frequency::Monthly,
+            flag: "*".to_string(),
+            payee: None,
+            narration: None,
+            postings: vec![],
+        };
+
+        // as of 2022-03-01, occurrences 0 (Jan), 1 (Feb) and 2 (Mar) have already
+        // happened, so the next upcoming one must be index 3 (Apr), not 0.
+        let upcoming = scheduled.upcoming_occurrences(date(2022, 3, 1), 2);
+        assert_eq!(upcoming, vec![(3, date(2022, 4, 1)), (4, date(2022, 5, 1))]);
+        assert_eq!(scheduled.occurrence_id(3), "rent:3");
+    }
+
+    #[test]
+    fn occurrences_through_skips_ids_already_seen() {
+        let scheduled = Scheduled {
+            id: "rent".to_string(),
+            start_date: date(2022, 1, 1),
+            frequency: Frequency::Monthly,
+            flag: "*".to_string(),
+            payee: None,
+            narration: None,
+            postings: vec![],
+        };
+
+        let mut already_seen = HashSet::new();
+        already_seen.insert("rent:0".to_string());
+
+        let occurrences = scheduled.occurrences_through(date(2022, 3, 1), &already_seen);
+
+        assert_eq!(
+            occurrences,
+            vec![(1, date(2022, 2, 1), "rent:1".to_string()), (2, date(2022, 3, 1), "rent:2".to_string())]
+        );
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+
+use chrono::{Datelike, NaiveDate};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::core::data::{Budget, Scheduled};
+use crate::core::inventory::AccountName;
+use crate::core::Currency;
+
+/// Tracks, for a single `Budget` envelope, the per-month figures needed to answer
+/// "how much is left in this envelope" without replaying the whole ledger.
+///
+/// `budgeted` and `activity` are accumulated directly by `DirectiveProcess` impls as
+/// directives are processed (`Budget` for the former, `Transaction`/`Scheduled` for
+/// the latter, via `accumulate_posting`); `available` is then derived by
+/// [`BudgetEnvelopeSnapshot::rebuild_available`], which `accumulate_posting` re-runs
+/// every time `activity` changes. `activity` is accumulated as the *negative* of the
+/// posting's signed amount, since a bound (expense) account's postings are positive
+/// when money is spent - so spending should reduce `available`, not inflate it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BudgetMonthSnapshot {
+    pub budgeted: Decimal,
+    pub activity: Decimal,
+    pub available: Decimal,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BudgetEnvelopeSnapshot {
+    pub accounts: Vec<AccountName>,
+    /// The single commodity this envelope tracks activity in, from the `Budget`
+    /// directive's own `commodity` field (see [`apply_budget_directive`]). A posting
+    /// only counts toward this envelope if it is in this commodity - see
+    /// [`envelope_applies_to`] - so an envelope whose bound accounts post in more than
+    /// one currency doesn't silently sum amounts across currencies.
+    pub commodity: Currency,
+    pub months: HashMap<NaiveDate, BudgetMonthSnapshot>,
+}
+
+impl BudgetEnvelopeSnapshot {
+    /// Recomputes `available` for every tracked month in date order, carrying the
+    /// clamped surplus/shortfall of month `m - 1` into month `m`. Cash overspending
+    /// (`available < 0`) is not carried forward as a negative balance; instead it is
+    /// left to reduce "To Be Budgeted" for the next month, so `carryover` is clamped
+    /// at zero rather than rolled over negative.
+    pub fn rebuild_available(&mut self) {
+        let mut months: Vec<NaiveDate> = self.months.keys().cloned().collect();
+        months.sort();
+
+        let mut carryover = Decimal::ZERO;
+        for month in months {
+            let snapshot = self.months.get_mut(&month).expect("month key just collected");
+            snapshot.available = carryover + snapshot.budgeted + snapshot.activity;
+            carryover = snapshot.available.max(Decimal::ZERO);
+        }
+    }
+}
+
+/// Merges a `Budget` directive's declared accounts, commodity and monthly
+/// allocations into the envelope's running snapshot, without disturbing activity
+/// recorded so far.
+pub fn apply_budget_directive(envelope: &mut BudgetEnvelopeSnapshot, directive: &Budget) {
+    envelope.accounts = directive.accounts.clone();
+    envelope.commodity = directive.commodity.clone();
+    for (month, assigned) in &directive.monthly_assigned {
+        envelope.months.entry(*month).or_default().budgeted = *assigned;
+    }
+}
+
+/// Whether a posting against `account_name` in `posting_currency` should count
+/// toward `envelope`'s `activity`: both bound to one of the envelope's `accounts` and
+/// posted in the envelope's own `commodity`.
+pub fn envelope_applies_to(envelope: &BudgetEnvelopeSnapshot, account_name: &AccountName, posting_currency: &Currency) -> bool {
+    envelope.accounts.iter().any(|bound| bound == account_name) && &envelope.commodity == posting_currency
+}
+
+/// Sum, across every tracked envelope-month of every envelope, of the month's
+/// explicit `budgeted` allocation plus any overspend in that month (a negative
+/// `available`, computed by [`BudgetEnvelopeSnapshot::rebuild_available`] before its
+/// carryover-clamping). The overspend term is what makes cash spent beyond an
+/// envelope's allocation reduce "To Be Budgeted" for future months instead of simply
+/// vanishing, as `rebuild_available`'s own carryover-clamping does locally within an
+/// envelope. Call after anything that can change a month's `budgeted` or `available`
+/// - a `Budget` directive or a posting against a bound account (see
+/// `core::process::accumulate_posting`) - to keep `Ledger::total_budgeted` current.
+pub fn total_effective_budgeted(budgets: &HashMap<String, BudgetEnvelopeSnapshot>) -> Decimal {
+    budgets
+        .values()
+        .flat_map(|envelope| envelope.months.values())
+        .map(|month| month.budgeted + (-month.available).max(Decimal::ZERO))
+        .sum()
+}
+
+/// Normalizes any date to the first day of its month, the granularity budgets are
+/// tracked at.
+pub fn month_of(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).expect("day 1 is valid in every month")
+}
+
+impl Ledger {
+    /// The operating currencies configured via `option "operating_currency" "..."`,
+    /// as a comma-separated list. Falls back to an empty list when unset, e.g. for a
+    /// ledger that only ever uses a single implicit currency.
+    pub fn operating_currencies(&self) -> Vec<Currency> {
+        self.configs
+            .get("operating_currency")
+            .map(|value| value.split(',').map(|currency| currency.trim().to_string()).collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn month(year: i32, month: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, 1).unwrap()
+    }
+
+    #[test]
+    fn spending_reduces_available_rather_than_inflating_it() {
+        let mut envelope = BudgetEnvelopeSnapshot::default();
+        envelope.months.entry(month(2022, 1)).or_default().budgeted = Decimal::new(1500, 0);
+        // a 400-unit expense posting accumulates as activity = -400, matching
+        // `accumulate_posting`'s sign convention.
+        envelope.months.entry(month(2022, 1)).or_default().activity = Decimal::new(-400, 0);
+
+        envelope.rebuild_available();
+
+        assert_eq!(envelope.months[&month(2022, 1)].available, Decimal::new(1100, 0));
+    }
+
+    #[test]
+    fn overspend_is_clamped_at_zero_rather_than_carried_forward() {
+        let mut envelope = BudgetEnvelopeSnapshot::default();
+        envelope.months.entry(month(2022, 1)).or_default().budgeted = Decimal::new(1000, 0);
+        envelope.months.entry(month(2022, 1)).or_default().activity = Decimal::new(-1500, 0);
+        envelope.months.entry(month(2022, 2)).or_default().budgeted = Decimal::new(1000, 0);
+
+        envelope.rebuild_available();
+
+        assert_eq!(envelope.months[&month(2022, 1)].available, Decimal::new(-500, 0));
+        // January overspent, so nothing carries into February - it is not left
+        // negative either, it just doesn't get a boost from January's shortfall.
+        assert_eq!(envelope.months[&month(2022, 2)].available, Decimal::new(1000, 0));
+    }
+
+    #[test]
+    fn surplus_rolls_forward_into_next_month() {
+        let mut envelope = BudgetEnvelopeSnapshot::default();
+        envelope.months.entry(month(2022, 1)).or_default().budgeted = Decimal::new(1000, 0);
+        envelope.months.entry(month(2022, 1)).or_default().activity = Decimal::new(-400, 0);
+        envelope.months.entry(month(2022, 2)).or_default().budgeted = Decimal::new(1000, 0);
+        envelope.months.entry(month(2022, 2)).or_default().activity = Decimal::new(-200, 0);
+
+        envelope.rebuild_available();
+
+        assert_eq!(envelope.months[&month(2022, 1)].available, Decimal::new(600, 0));
+        assert_eq!(envelope.months[&month(2022, 2)].available, Decimal::new(1400, 0));
+    }
+
+    #[test]
+    fn envelope_only_applies_to_its_own_bound_accounts_and_commodity() {
+        let mut envelope = BudgetEnvelopeSnapshot::default();
+        envelope.accounts = vec!["Expenses:Food:Groceries".to_string()];
+        envelope.commodity = "CNY".to_string();
+
+        assert!(envelope_applies_to(&envelope, &"Expenses:Food:Groceries".to_string(), &"CNY".to_string()));
+        assert!(!envelope_applies_to(&envelope, &"Expenses:Food:Groceries".to_string(), &"USD".to_string()));
+        assert!(!envelope_applies_to(&envelope, &"Expenses:Rent".to_string(), &"CNY".to_string()));
+    }
+
+    #[test]
+    fn total_effective_budgeted_includes_overspend_so_it_reduces_to_be_budgeted() {
+        let mut groceries = BudgetEnvelopeSnapshot::default();
+        groceries.months.entry(month(2022, 1)).or_default().budgeted = Decimal::new(1000, 0);
+        groceries.months.entry(month(2022, 1)).or_default().activity = Decimal::new(-1500, 0);
+        groceries.rebuild_available();
+
+        let mut rent = BudgetEnvelopeSnapshot::default();
+        rent.months.entry(month(2022, 1)).or_default().budgeted = Decimal::new(2000, 0);
+        rent.rebuild_available();
+
+        let mut budgets = HashMap::new();
+        budgets.insert("Groceries".to_string(), groceries);
+        budgets.insert("Rent".to_string(), rent);
+
+        // Groceries: 1000 budgeted, overspent by 500 (available -500) -> effectively
+        // 1500. Rent: 2000 budgeted, no activity -> effectively 2000. Total: 3500.
+        assert_eq!(total_effective_budgeted(&budgets), Decimal::new(3500, 0));
+    }
+}
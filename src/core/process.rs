@@ -1,14 +1,15 @@
 use crate::core::amount::Amount;
-use crate::core::data::{Balance, Close, Commodity, Document, Open, Options, Price, Transaction};
+use crate::core::data::{Balance, Budget, Close, Commodity, Document, Open, Options, Price, Scheduled, Transaction};
 use crate::core::inventory::AccountName;
 use crate::core::ledger::{
-    AccountInfo, AccountSnapshot, AccountStatus, CurrencyInfo, DailyAccountSnapshot, DocumentType, Ledger, LedgerError,
+    apply_budget_directive, envelope_applies_to, month_of, total_effective_budgeted, AccountInfo, AccountSnapshot,
+    AccountStatus, BudgetEnvelopeSnapshot, CurrencyInfo, DailyAccountSnapshot, DocumentType, Ledger, LedgerError,
 };
 use crate::core::utils::price_grip::DatedPriceGrip;
 use crate::error::ZhangResult;
 use chrono::NaiveDate;
 use log::error;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ops::{Neg, Sub};
 use std::sync::{Arc, RwLock as StdRwLock};
 
@@ -122,17 +123,166 @@ impl DirectiveProcess for Transaction {
             date,
         );
 
+        if let Some(import_id) = self.meta.get("import-id") {
+            ledger.import_ids.insert(import_id.clone().to_plain_string());
+        }
+
         for txn_posting in self.txn_postings() {
-            let target_account_snapshot = ledger
-                .snapshot
-                .entry(txn_posting.account_name())
-                .or_insert_with(|| context.default_account_snapshot());
-            target_account_snapshot.add_amount(txn_posting.units());
+            accumulate_posting(ledger, context, date, txn_posting.account_name(), txn_posting.units());
+        }
+        Ok(())
+    }
+}
+
+/// Applies a single posting's amount to the account snapshot, the matching budget
+/// envelope's activity (if the account is bound to one, in the envelope's own
+/// commodity) and the income accumulator. This is the accumulation path shared by
+/// [`Transaction::process`] and, for materialized occurrences, [`Scheduled::process`]
+/// / `expand_scheduled_occurrences`. An envelope's `available` is recomputed here,
+/// right after `activity` changes, rather than only when a new `Budget` directive
+/// appears - otherwise `available` would go stale for every envelope touched by a
+/// transaction dated after its last `Budget` directive. `ledger.total_budgeted` is
+/// likewise recomputed from `ledger.budgets` (see [`total_effective_budgeted`])
+/// whenever an envelope's activity changes it, so cash overspending reduces "To Be
+/// Budgeted" rather than only clamping the envelope's own carryover.
+fn accumulate_posting(
+    ledger: &mut Ledger, context: &mut ProcessContext, date: NaiveDate, account_name: AccountName, units: Amount,
+) {
+    if account_name.starts_with("Income:") {
+        // `Income:`-prefixed accounts are negative when real income is earned - the
+        // mirror image of an expense account's positive-when-spent convention - so
+        // the contribution to `total_income` negates the raw posting amount.
+        // Otherwise `total_income` would run increasingly negative as real income
+        // accrues, inverting `to_be_budgeted = total_income - total_budgeted`
+        // (`server::model`) into a negative "ready to assign" figure.
+        ledger.total_income -= units.number.clone();
+    }
+
+    let mut any_envelope_changed = false;
+    for envelope in ledger.budgets.values_mut() {
+        if envelope_applies_to(envelope, &account_name, &units.currency) {
+            let month_snapshot = envelope.months.entry(month_of(date)).or_default();
+            // Expense-account postings are positive when money is spent (the same
+            // signed-amount convention `target_account_snapshot.add_amount` below
+            // relies on), so spending must subtract from the envelope, not add to it.
+            month_snapshot.activity -= units.number.clone();
+            envelope.rebuild_available();
+            any_envelope_changed = true;
+        }
+    }
+    if any_envelope_changed {
+        ledger.total_budgeted = total_effective_budgeted(&ledger.budgets);
+    }
+
+    let target_account_snapshot = ledger
+        .snapshot
+        .entry(account_name)
+        .or_insert_with(|| context.default_account_snapshot());
+    target_account_snapshot.add_amount(units);
+}
+
+impl DirectiveProcess for Budget {
+    fn process(&mut self, ledger: &mut Ledger, _context: &mut ProcessContext) -> ZhangResult<()> {
+        let envelope = ledger
+            .budgets
+            .entry(self.name.clone())
+            .or_insert_with(BudgetEnvelopeSnapshot::default);
+        apply_budget_directive(envelope, self);
+
+        for envelope in ledger.budgets.values_mut() {
+            envelope.rebuild_available();
+        }
+        // Recomputed from every envelope's current per-month `budgeted` (plus
+        // overspend, see `total_effective_budgeted`) rather than accumulated
+        // directive-by-directive: `apply_budget_directive` overwrites (not adds to) a
+        // month's allocation, so a directive revising an already-budgeted month would
+        // otherwise double-count the old allocation here.
+        ledger.total_budgeted = total_effective_budgeted(&ledger.budgets);
+        Ok(())
+    }
+}
+
+impl DirectiveProcess for Scheduled {
+    fn process(&mut self, ledger: &mut Ledger, context: &mut ProcessContext) -> ZhangResult<()> {
+        ledger.scheduled.insert(self.id.clone(), self.clone());
+
+        // `context.target_day` is only the date of whichever directive most recently
+        // ran through `record_daily_snapshot` (see `Transaction`/`Balance::process`),
+        // not an "as of" cutoff for the whole ledger - a `Scheduled` directive with no
+        // earlier-dated `Transaction`/`Balance` before it in the sorted pass may only
+        // expand its earliest occurrences here. Falling back to the wall clock to
+        // paper over that (a prior version of this fix did) is worse: it lets this
+        // directive's own `record_daily_snapshot` call flush a `daily_snapshot` entry
+        // keyed at "today" before chronologically-earlier directives in the sorted
+        // pass have even run, corrupting that entry, and makes processing depend on
+        // when it happens to run rather than solely on the ledger's own content.
+        // `core::cache::load_ledger` tops up whatever this misses via
+        // [`expand_scheduled_occurrences`], once the full pass is done and the
+        // ledger's true latest date is known.
+        let as_of = context.target_day.unwrap_or(self.start_date);
+        let already_seen: HashSet<String> = ledger.scheduled_occurrences.keys().cloned().collect();
+
+        for (_occurrence, date, id) in self.occurrences_through(as_of, &already_seen) {
+            ledger.scheduled_occurrences.insert(id, date);
+
+            record_daily_snapshot(&mut ledger.snapshot, &mut ledger.daily_snapshot, &mut context.target_day, date);
+            for posting in &self.postings {
+                accumulate_posting(ledger, context, date, posting.account.clone(), posting.amount.clone());
+            }
         }
+
         Ok(())
     }
 }
 
+/// The ledger's own latest known date: the max of every date already recorded in
+/// `daily_snapshot` and every registered template's own `start_date`, so a ledger made
+/// up of nothing but `Scheduled` directives still materializes at least their first
+/// occurrence. Used as the "as of" cutoff for [`expand_scheduled_occurrences`].
+/// Derived solely from the ledger's own content, never the wall clock (see the
+/// comment inside `Scheduled::process` for why that matters).
+pub(crate) fn scheduled_as_of_date(ledger: &Ledger) -> Option<NaiveDate> {
+    let known_dates = ledger.daily_snapshot.dates();
+    known_dates
+        .iter()
+        .copied()
+        .chain(ledger.scheduled.values().map(|scheduled| scheduled.start_date))
+        .max()
+}
+
+/// Tops up every registered `Scheduled` template (`ledger.scheduled`) with whichever
+/// occurrences up to and including `as_of` haven't already been recorded in
+/// `ledger.scheduled_occurrences`. `Scheduled::process` can only expand through
+/// whatever `target_day` happened to be at its own position in the sorted
+/// `DirectiveProcess` pass, which may be far earlier than the ledger's actual latest
+/// entry; this is meant to run once, after `Ledger::load`'s full replay has finished
+/// and every directive's date is known (see `core::cache::load_ledger`).
+/// Already-recorded occurrences are left untouched, so calling this on a ledger that
+/// already expanded everything inline is a no-op.
+pub(crate) fn expand_scheduled_occurrences(ledger: &mut Ledger, as_of: Option<NaiveDate>) {
+    let Some(as_of) = as_of else {
+        return;
+    };
+
+    let mut context = ProcessContext { target_day: None, prices: ledger.prices.clone() };
+    let already_seen: HashSet<String> = ledger.scheduled_occurrences.keys().cloned().collect();
+    let templates: Vec<Scheduled> = ledger.scheduled.values().cloned().collect();
+
+    for template in &templates {
+        for (_occurrence, date, id) in template.occurrences_through(as_of, &already_seen) {
+            ledger.scheduled_occurrences.insert(id, date);
+            record_daily_snapshot(&mut ledger.snapshot, &mut ledger.daily_snapshot, &mut context.target_day, date);
+            for posting in &template.postings {
+                accumulate_posting(ledger, &mut context, date, posting.account.clone(), posting.amount.clone());
+            }
+        }
+    }
+
+    if let Some(last_day) = context.target_day {
+        ledger.daily_snapshot.insert_snapshot(last_day, ledger.snapshot.clone());
+    }
+}
+
 impl DirectiveProcess for Balance {
     fn process(&mut self, ledger: &mut Ledger, context: &mut ProcessContext) -> ZhangResult<()> {
         match self {
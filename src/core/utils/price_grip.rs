@@ -0,0 +1,17 @@
+use std::ops::RangeInclusive;
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+use crate::core::Currency;
+use crate::error::ZhangResult;
+
+/// A source of historical commodity prices, pluggable so the `prices` back-fill
+/// subsystem can be pointed at different quote sources without touching the
+/// diff/dedupe logic that decides which dates actually need fetching.
+pub trait PriceProvider {
+    /// Returns every known `(date, price)` pair for `base` expressed in `quote`,
+    /// restricted to `range`. Implementations may return fewer dates than requested
+    /// (e.g. markets closed on weekends); callers must not assume full coverage.
+    fn fetch(&self, base: Currency, quote: Currency, range: RangeInclusive<NaiveDate>) -> ZhangResult<Vec<(NaiveDate, Decimal)>>;
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::NaiveDate;
+use regex::Regex;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+use crate::core::ledger::Ledger;
+use crate::error::{ZhangError, ZhangResult};
+use crate::importer::{import_id, Importer};
+
+/// One payee-matching rule used to pick a posting's other-leg account instead of
+/// falling back to `default_target_account`. Rules are tried in config order; the
+/// first whose `payee_regex` matches wins.
+#[derive(Deserialize)]
+pub struct RoutingRule {
+    pub payee_regex: String,
+    pub account: String,
+}
+
+/// Maps a CSV export's columns onto the fields a transaction needs. Columns are
+/// referenced by header name rather than position so a config survives the bank
+/// reordering its export.
+#[derive(Deserialize)]
+pub struct CsvImportConfig {
+    /// path (relative to the process's current working directory) to the zhang ledger
+    /// entry file that already-imported ids are checked against and new
+    /// transactions are rendered for - the CSV export handed to `run` is the bank's
+    /// statement, not the ledger itself.
+    pub ledger: PathBuf,
+    pub account: String,
+    pub date_column: String,
+    #[serde(default = "default_date_format")]
+    pub date_format: String,
+    pub amount_column: String,
+    pub commodity: String,
+    pub payee_column: Option<String>,
+    pub narration_column: Option<String>,
+    pub default_target_account: String,
+    #[serde(default)]
+    pub routing_rules: Vec<RoutingRule>,
+}
+
+fn default_date_format() -> String {
+    "%Y-%m-%d".to_string()
+}
+
+impl CsvImportConfig {
+    fn target_account_for(&self, payee: &str) -> ZhangResult<String> {
+        for rule in &self.routing_rules {
+            let regex = Regex::new(&rule.payee_regex).map_err(|error| ZhangError::PluginError(error.to_string()))?;
+            if regex.is_match(payee) {
+                return Ok(rule.account.clone());
+            }
+        }
+        Ok(self.default_target_account.clone())
+    }
+}
+
+pub struct CsvImporter;
+
+impl Importer for CsvImporter {
+    fn run(&self, file: PathBuf, config: PathBuf) -> ZhangResult<()> {
+        run(file, config)
+    }
+}
+
+/// Converts every row of `file` into a zhang transaction and prints the ones whose
+/// import id isn't already present in the ledger to stdout. Rows that have already been
+/// imported (by a previous, possibly overlapping, export from the same bank) are
+/// silently skipped, making repeated imports of the same statement idempotent.
+pub fn run(file: PathBuf, config: PathBuf) -> ZhangResult<()> {
+    let config: CsvImportConfig = toml::from_str(&fs::read_to_string(config)?).map_err(|error| ZhangError::PluginError(error.to_string()))?;
+    let ledger = Ledger::load(config.ledger.clone()).expect("Cannot load ledger");
+
+    let mut reader = ::csv::ReaderBuilder::new().from_path(&file)?;
+    let mut occurrence_by_amount_and_date: HashMap<(i64, NaiveDate), u32> = HashMap::new();
+    let mut generated = String::new();
+
+    for record in reader.deserialize::<HashMap<String, String>>() {
+        let record = record.map_err(|error| ZhangError::PluginError(error.to_string()))?;
+
+        let date = NaiveDate::parse_from_str(&record[&config.date_column], &config.date_format)
+            .map_err(|error| ZhangError::PluginError(error.to_string()))?;
+        let amount: Decimal = record[&config.amount_column]
+            .parse()
+            .map_err(|_| ZhangError::PluginError(format!("cannot parse amount {}", &record[&config.amount_column])))?;
+        let milliunit_amount = (amount * Decimal::from(1000)).round().to_i64().unwrap_or_default();
+
+        let occurrence = occurrence_by_amount_and_date.entry((milliunit_amount, date)).or_insert(0);
+        let id = import_id(milliunit_amount, date, *occurrence);
+        *occurrence += 1;
+
+        if ledger.import_ids.contains(&id) {
+            continue;
+        }
+
+        let payee = config
+            .payee_column
+            .as_ref()
+            .and_then(|column| record.get(column))
+            .cloned()
+            .unwrap_or_default();
+        let narration = config
+            .narration_column
+            .as_ref()
+            .and_then(|column| record.get(column))
+            .cloned()
+            .unwrap_or_default();
+        let target_account = config.target_account_for(&payee)?;
+
+        generated.push_str(&format!(
+            "{date} * \"{payee}\" \"{narration}\"\n  import-id: \"{id}\"\n  {account}  {amount} {commodity}\n  {target_account}\n\n",
+            date = date.format("%Y-%m-%d"),
+            payee = payee.replace('"', "'"),
+            narration = narration.replace('"', "'"),
+            id = id,
+            account = config.account,
+            amount = amount,
+            commodity = config.commodity,
+            target_account = target_account,
+        ));
+    }
+
+    if !generated.is_empty() {
+        print!("{}", generated);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_with_rules(routing_rules: Vec<RoutingRule>) -> CsvImportConfig {
+        CsvImportConfig {
+            ledger: PathBuf::from("main.zhang"),
+            account: "Assets:Bank".to_string(),
+            date_column: "Date".to_string(),
+            date_format: default_date_format(),
+            amount_column: "Amount".to_string(),
+            commodity: "CNY".to_string(),
+            payee_column: None,
+            narration_column: None,
+            default_target_account: "Expenses:Unknown".to_string(),
+            routing_rules,
+        }
+    }
+
+    #[test]
+    fn first_matching_routing_rule_wins() {
+        let config = config_with_rules(vec![
+            RoutingRule { payee_regex: "Coffee".to_string(), account: "Expenses:Food:Coffee".to_string() },
+            RoutingRule { payee_regex: ".*".to_string(), account: "Expenses:Catchall".to_string() },
+        ]);
+
+        assert_eq!(config.target_account_for("Blue Bottle Coffee").unwrap(), "Expenses:Food:Coffee");
+        assert_eq!(config.target_account_for("Some Other Shop").unwrap(), "Expenses:Catchall");
+    }
+
+    #[test]
+    fn falls_back_to_default_target_account_when_no_rule_matches() {
+        let config = config_with_rules(vec![RoutingRule { payee_regex: "Coffee".to_string(), account: "Expenses:Food:Coffee".to_string() }]);
+
+        assert_eq!(config.target_account_for("Landlord").unwrap(), "Expenses:Unknown");
+    }
+}
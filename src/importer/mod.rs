@@ -0,0 +1,35 @@
+pub mod csv;
+pub mod wechat;
+
+use std::path::PathBuf;
+
+use crate::error::ZhangResult;
+
+/// Common interface for anything that turns an external export file into
+/// zhang-formatted directives, so new sources (csv, wechat,
+/// ...) can be added without the CLI needing to know their internals.
+pub trait Importer {
+    fn run(&self, file: PathBuf, config: PathBuf) -> ZhangResult<()>;
+}
+
+/// Builds a deterministic, YNAB-style import id for a single imported row:
+/// `AVARO:<milliunit amount>:<iso date>:<occurrence>`. `occurrence` must be supplied
+/// by the caller, incrementing per distinct `(milliunit_amount, date)` pair seen
+/// within the same import run, so that otherwise-identical rows (e.g. two identical
+/// coffee purchases on the same day) still get distinct, stable ids.
+pub fn import_id(milliunit_amount: i64, date: chrono::NaiveDate, occurrence: u32) -> String {
+    format!("AVARO:{}:{}:{}", milliunit_amount, date.format("%Y-%m-%d"), occurrence)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_is_stable_and_occurrence_disambiguates_identical_rows() {
+        let date = chrono::NaiveDate::from_ymd_opt(2022, 1, 1).unwrap();
+
+        assert_eq!(import_id(-4500, date, 0), "AVARO:-4500:2022-01-01:0");
+        assert_ne!(import_id(-4500, date, 0), import_id(-4500, date, 1));
+    }
+}
@@ -0,0 +1,67 @@
+use std::ops::RangeInclusive;
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+use crate::core::utils::price_grip::PriceProvider;
+use crate::core::Currency;
+use crate::error::{ZhangError, ZhangResult};
+
+/// Fetches historical prices from an HTTP quote API. This is currently the only
+/// `PriceProvider` implementation, constructed by the `fetch-prices` command from
+/// `--provider-url`/`--api-key`, falling back to the ledger's own
+/// `price_provider_url`/`price_provider_api_key` config options when those flags are
+/// omitted (see `FetchPricesOpts::run`), so a recurring back-fill doesn't have to
+/// repeat them on every invocation.
+pub struct HttpPriceProvider {
+    base_url: String,
+    api_key: Option<String>,
+}
+
+impl HttpPriceProvider {
+    pub fn new(base_url: String, api_key: Option<String>) -> Self {
+        HttpPriceProvider { base_url, api_key }
+    }
+}
+
+#[derive(Deserialize)]
+struct HistoricalPricePoint {
+    date: NaiveDate,
+    price: Decimal,
+}
+
+impl PriceProvider for HttpPriceProvider {
+    fn fetch(&self, base: Currency, quote: Currency, range: RangeInclusive<NaiveDate>) -> ZhangResult<Vec<(NaiveDate, Decimal)>> {
+        let mut request = reqwest::blocking::Client::new().get(format!("{}/historical-prices", self.base_url)).query(&[
+            ("base", base.as_str()),
+            ("quote", quote.as_str()),
+            ("from", &range.start().format("%Y-%m-%d").to_string()),
+            ("to", &range.end().format("%Y-%m-%d").to_string()),
+        ]);
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+
+        let response = request.send().map_err(|error| ZhangError::PluginError(error.to_string()))?;
+        let points: Vec<HistoricalPricePoint> = response.json().map_err(|error| ZhangError::PluginError(error.to_string()))?;
+        Ok(points.into_iter().map(|point| (point.date, point.price)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn historical_price_points_deserialize_from_the_expected_json_shape() {
+        let points: Vec<HistoricalPricePoint> = serde_json::from_str(
+            r#"[{"date": "2022-01-01", "price": "1.2345"}, {"date": "2022-01-02", "price": "1.25"}]"#,
+        )
+        .unwrap();
+
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[0].date, NaiveDate::from_ymd_opt(2022, 1, 1).unwrap());
+        assert_eq!(points[0].price, Decimal::new(12345, 4));
+    }
+}
@@ -0,0 +1,89 @@
+mod http;
+
+use std::collections::BTreeSet;
+
+pub use http::HttpPriceProvider;
+
+use crate::core::ledger::Ledger;
+use crate::core::utils::price_grip::{DatedPriceGrip, PriceProvider};
+use crate::core::Currency;
+use crate::error::ZhangResult;
+
+/// Outcome of back-filling a single `(commodity, operating-currency)` pair, so the
+/// CLI can report what it actually fetched instead of running silently.
+#[derive(Debug)]
+pub struct PriceBackfillReport {
+    pub commodity: Currency,
+    pub operating_currency: Currency,
+    pub inserted: usize,
+}
+
+/// For every commodity declared on some account in `ledger`, against every configured
+/// operating currency, fetches and inserts whichever dates `grip` is missing out of
+/// the dates `ledger` actually needs a quote for (transaction and balance dates, i.e.
+/// the keys of its daily snapshot). A date already present in `grip` - whether it
+/// came from an explicit `Price` directive or an earlier back-fill run - is left
+/// untouched: since `Price` directives are processed into `grip` before this ever
+/// runs, that is what makes the ledger's own prices always win over fetched ones, and
+/// makes a second call against the same `grip` a no-op. Fetched prices are inserted
+/// into `grip` only; nothing is written back to the ledger's files.
+///
+/// Commodities are sourced from `ledger.accounts`' declared currencies rather than
+/// `ledger.currencies` (the map built from `Commodity` directives); the former is
+/// what `core::cache::LedgerCache` stores, the latter is not cached.
+pub fn backfill_ledger_prices(ledger: &Ledger, provider: &dyn PriceProvider, grip: &mut DatedPriceGrip) -> ZhangResult<Vec<PriceBackfillReport>> {
+    let needed_dates = ledger.daily_snapshot.dates();
+    let (Some(&first), Some(&last)) = (needed_dates.iter().min(), needed_dates.iter().max()) else {
+        return Ok(vec![]);
+    };
+    let range = first..=last;
+
+    let operating_currencies = ledger.operating_currencies();
+    let mut reports = Vec::new();
+
+    let commodities: BTreeSet<Currency> = ledger.accounts.values().flat_map(|info| info.currencies.iter().cloned()).collect();
+
+    for commodity in &commodities {
+        for operating_currency in &operating_currencies {
+            if commodity == operating_currency {
+                continue;
+            }
+
+            let missing: BTreeSet<_> = needed_dates
+                .iter()
+                .copied()
+                .filter(|date| {
+                    grip.get(date.and_hms_opt(0, 0, 0).expect("midnight always exists"), commodity, operating_currency)
+                        .is_none()
+                })
+                .collect();
+            if missing.is_empty() {
+                continue;
+            }
+
+            let fetched = provider.fetch(commodity.clone(), operating_currency.clone(), range.clone())?;
+            let mut inserted = 0;
+            for (date, price) in fetched {
+                if missing.contains(&date) {
+                    grip.insert(
+                        date.and_hms_opt(0, 0, 0).expect("midnight always exists"),
+                        commodity.clone(),
+                        operating_currency.clone(),
+                        price,
+                    );
+                    inserted += 1;
+                }
+            }
+
+            if inserted > 0 {
+                reports.push(PriceBackfillReport {
+                    commodity: commodity.clone(),
+                    operating_currency: operating_currency.clone(),
+                    inserted,
+                });
+            }
+        }
+    }
+
+    Ok(reports)
+}
@@ -0,0 +1,78 @@
+use std::sync::Arc;
+
+use async_graphql::{EmptyMutation, EmptySubscription, Object, Schema};
+use chrono::Local;
+use tokio::sync::RwLock;
+
+use crate::core::ledger::Ledger;
+use crate::server::response::{
+    BudgetMonthResponse, BudgetOverviewResponse, BudgetResponse, UpcomingScheduledTransactionResponse,
+};
+
+pub type LedgerSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+pub struct QueryRoot {
+    pub ledger: Arc<RwLock<Ledger>>,
+}
+
+#[Object]
+impl QueryRoot {
+    /// Envelope-budgeting overview: every envelope's per-month `{budgeted, activity,
+    /// available}`, plus the global "To Be Budgeted" figure.
+    async fn budgets(&self) -> BudgetOverviewResponse {
+        let ledger = self.ledger.read().await;
+
+        let budgets = ledger
+            .budgets
+            .iter()
+            .map(|(name, envelope)| {
+                let mut months: Vec<_> = envelope
+                    .months
+                    .iter()
+                    .map(|(month, snapshot)| BudgetMonthResponse {
+                        month: *month,
+                        budgeted: snapshot.budgeted.into(),
+                        activity: snapshot.activity.into(),
+                        available: snapshot.available.into(),
+                    })
+                    .collect();
+                months.sort_by_key(|month| month.month);
+
+                BudgetResponse {
+                    name: name.clone(),
+                    accounts: envelope.accounts.clone(),
+                    months,
+                }
+            })
+            .collect();
+
+        BudgetOverviewResponse {
+            to_be_budgeted: (ledger.total_income - ledger.total_budgeted).into(),
+            budgets,
+        }
+    }
+
+    /// The next 5 occurrences of every `Scheduled` template dated after today (by the
+    /// local clock), so the frontend can preview upcoming recurring transactions.
+    async fn upcoming_scheduled_transactions(&self) -> Vec<UpcomingScheduledTransactionResponse> {
+        let ledger = self.ledger.read().await;
+        let today = Local::now().naive_local().date();
+
+        ledger
+            .scheduled
+            .values()
+            .flat_map(|scheduled| {
+                scheduled
+                    .upcoming_occurrences(today, 5)
+                    .into_iter()
+                    .map(move |(occurrence, date)| UpcomingScheduledTransactionResponse {
+                        id: scheduled.occurrence_id(occurrence),
+                        scheduled_id: scheduled.id.clone(),
+                        date,
+                        payee: scheduled.payee.clone(),
+                        narration: scheduled.narration.clone(),
+                    })
+            })
+            .collect()
+    }
+}
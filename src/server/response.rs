@@ -233,3 +233,33 @@ pub struct CurrentStatisticResponse {
   pub  expense: AmountResponse
 }
 
+#[derive(Serialize)]
+pub struct BudgetMonthResponse {
+    pub month: NaiveDate,
+    pub budgeted: ZhangBigDecimal,
+    pub activity: ZhangBigDecimal,
+    pub available: ZhangBigDecimal,
+}
+
+#[derive(Serialize)]
+pub struct BudgetResponse {
+    pub name: String,
+    pub accounts: Vec<String>,
+    pub months: Vec<BudgetMonthResponse>,
+}
+
+#[derive(Serialize)]
+pub struct BudgetOverviewResponse {
+    pub to_be_budgeted: ZhangBigDecimal,
+    pub budgets: Vec<BudgetResponse>,
+}
+
+#[derive(Serialize)]
+pub struct UpcomingScheduledTransactionResponse {
+    pub id: String,
+    pub scheduled_id: String,
+    pub date: NaiveDate,
+    pub payee: Option<String>,
+    pub narration: Option<String>,
+}
+